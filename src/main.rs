@@ -1,24 +1,30 @@
+mod tui;
+
 use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
 use std::{
-    collections::HashSet,
+    collections::HashMap,
     fmt::{Display, Formatter},
-    io::stdin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-trait Search {
+pub(crate) trait Search {
     type Score: Ord + Eq + Copy;
     type Iter: Iterator<Item = Self>;
 
     fn score(&self) -> Self::Score;
     fn moves(&self) -> Self::Iter;
     fn game_over(&self) -> bool;
+    fn hash_key(&self) -> u64;
 }
 
 #[derive(Clone, Eq, PartialEq, Hash)]
-struct Grid<T> {
+pub(crate) struct Grid<T> {
     _data: Vec<T>,
-    width: usize,
-    height: usize,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
 }
 
 impl<T: Clone> Grid<T> {
@@ -30,12 +36,28 @@ impl<T: Clone> Grid<T> {
         }
     }
 
-    fn get(&self, x: usize, y: usize) -> &T {
-        &self._data[y * self.width + x]
+    pub(crate) fn rect(&self) -> Rect {
+        Rect::new(self.width, self.height)
+    }
+
+    pub(crate) fn contains(&self, coord: Coord) -> bool {
+        self.rect().contains(coord)
+    }
+
+    pub(crate) fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if self.contains(Coord::new(x, y)) {
+            Some(&self._data[y * self.width + x])
+        } else {
+            None
+        }
     }
 
-    fn get_mut(&mut self, x: usize, y: usize) -> &mut T {
-        &mut self._data[y * self.width + x]
+    pub(crate) fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if self.contains(Coord::new(x, y)) {
+            Some(&mut self._data[y * self.width + x])
+        } else {
+            None
+        }
     }
 }
 
@@ -43,7 +65,7 @@ impl<T: Clone + Display> Display for Grid<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.height {
             for x in 0..self.width {
-                write!(f, "{} ", self.get(x, y))?;
+                write!(f, "{} ", self.get(x, y).unwrap())?;
             }
             writeln!(f)?;
         }
@@ -51,8 +73,71 @@ impl<T: Clone + Display> Display for Grid<T> {
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Hash, Copy)]
-enum Piece {
+// A cell coordinate on a `Grid`/`Board`. Plain `usize`s, since cells are
+// never negative; the only place `i32` arithmetic happens is `offset`,
+// which is how callers step in a direction without underflowing.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Coord {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+}
+
+impl Coord {
+    pub(crate) fn new(x: usize, y: usize) -> Coord {
+        Coord { x, y }
+    }
+
+    // Steps `(dx, dy)` away from this coordinate, or `None` if that would
+    // underflow `usize`. Doesn't know about any grid's upper bounds; pair
+    // with `Rect::contains` (or `Rect::line`, which already does) for that.
+    pub(crate) fn offset(&self, dx: i32, dy: i32) -> Option<Coord> {
+        let x = self.x as i32 + dx;
+        let y = self.y as i32 + dy;
+        if x >= 0 && y >= 0 {
+            Some(Coord::new(x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+}
+
+// The bounds of a `Grid`, independent of what it stores. Gives the
+// scanning helpers below one place to apply bounds checks instead of every
+// caller open-coding `x < width && y < height`.
+#[derive(Clone, Copy)]
+pub(crate) struct Rect {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl Rect {
+    pub(crate) fn new(width: usize, height: usize) -> Rect {
+        Rect { width, height }
+    }
+
+    pub(crate) fn contains(&self, coord: Coord) -> bool {
+        coord.x < self.width && coord.y < self.height
+    }
+
+    // Every coordinate in the rect, row-major.
+    pub(crate) fn cells(&self) -> impl Iterator<Item = Coord> + '_ {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| Coord::new(x, y)))
+    }
+
+    // Coordinates stepping away from `from` in direction `(dx, dy)`, one
+    // step at a time, stopping as soon as a step leaves the rect. `from`
+    // itself is not included.
+    pub(crate) fn line(&self, from: Coord, dx: i32, dy: i32) -> impl Iterator<Item = Coord> + '_ {
+        let rect = *self;
+        (1i32..)
+            .map_while(move |d| from.offset(dx * d, dy * d))
+            .take_while(move |&c| rect.contains(c))
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Copy, Debug)]
+pub(crate) enum Piece {
     Red,
     Yellow,
     Empty,
@@ -79,18 +164,55 @@ impl Display for Piece {
     }
 }
 
-type Position = (usize, usize);
+pub(crate) type Position = (usize, usize);
+
+// Zobrist keys for one board size: a random u64 per (cell, color) pair plus
+// one extra key representing whose turn it is. Shared via `Arc` across every
+// `Board` cloned from the same game so the incremental hash stays comparable
+// between positions reached by different move orders.
+struct Zobrist {
+    keys: Vec<u64>,
+    side_to_move: u64,
+}
+
+impl Zobrist {
+    fn new(width: usize, height: usize) -> Zobrist {
+        let mut rng = rand::thread_rng();
+        Zobrist {
+            keys: (0..width * height * 2).map(|_| rng.gen()).collect(),
+            side_to_move: rng.gen(),
+        }
+    }
+
+    fn piece_key(&self, x: usize, y: usize, width: usize, piece: Piece) -> u64 {
+        let color_index = match piece {
+            Piece::Red => 0,
+            Piece::Yellow => 1,
+            Piece::Empty => return 0,
+        };
+        self.keys[(y * width + x) * 2 + color_index]
+    }
+}
 
-#[derive(Clone, Eq)]
-struct Board {
-    grid: Grid<Piece>,
-    drop_zones: Vec<usize>,
-    threats: Vec<(Position, Piece)>,
-    winner: Option<Piece>,
-    next_move: Piece,
-    show_threats: bool,
+#[derive(Clone)]
+pub(crate) struct Board {
+    pub(crate) grid: Grid<Piece>,
+    pub(crate) drop_zones: Vec<usize>,
+    pub(crate) threats: Vec<(Position, Piece)>,
+    pub(crate) winner: Option<Piece>,
+    pub(crate) next_move: Piece,
+    pub(crate) show_threats: bool,
+    // Columns in the order they should be tried during search, center-first,
+    // so alpha-beta sees the historically strongest moves earliest.
+    move_order: Vec<usize>,
+    zobrist: Arc<Zobrist>,
+    hash: u64,
+    // Number of same-color pieces in a row needed to win (Connect-N).
+    win_length: usize,
 }
 
+const DEFAULT_WIN_LENGTH: usize = 4;
+
 struct Tallies {
     red: Vec<Position>,
     yellow: Vec<Position>,
@@ -108,7 +230,18 @@ impl Tallies {
 }
 
 impl Board {
-    fn new_with_size(width: usize, height: usize) -> Board {
+    pub(crate) fn new_with_options(width: usize, height: usize, win_length: usize) -> Board {
+        // A win_length of 0 would make `update`'s `win_length - 1` underflow,
+        // and isn't a meaningful game anyway, so floor it at 1.
+        let win_length = win_length.max(1);
+        let zobrist = Arc::new(Zobrist::new(width, height));
+        let mut move_order: Vec<usize> = (0..width).collect();
+        let center = (width as f64 - 1.0) / 2.0;
+        move_order.sort_by(|&a, &b| {
+            let da = (a as f64 - center).abs();
+            let db = (b as f64 - center).abs();
+            da.partial_cmp(&db).unwrap()
+        });
         Board {
             grid: Grid::new(width, height, Piece::Empty),
             drop_zones: vec![height; width],
@@ -116,19 +249,19 @@ impl Board {
             winner: None,
             next_move: Piece::Yellow,
             show_threats: false,
+            move_order,
+            hash: zobrist.side_to_move,
+            zobrist,
+            win_length,
         }
     }
 
-    fn new() -> Board {
-        Board::new_with_size(7, 6)
-    }
-
-    // fn get(&self, x: usize, y: usize) -> &Piece {
-    //     &self.grid.get(x, y)
-    // }
-
     fn set(&mut self, x: usize, y: usize, piece: Piece) {
-        *self.grid.get_mut(x, y) = piece;
+        if !self.grid.contains(Coord::new(x, y)) {
+            return;
+        }
+        self.hash ^= self.zobrist.piece_key(x, y, self.grid.width, piece);
+        *self.grid.get_mut(x, y).unwrap() = piece;
         if self.threats.contains(&((x, y), piece)) {
             self.winner = Some(piece);
         }
@@ -136,12 +269,13 @@ impl Board {
         self.update(x, y);
     }
 
-    fn drop(&mut self, x: usize) -> Option<usize> {
+    pub(crate) fn drop(&mut self, x: usize) -> Option<usize> {
         if self.drop_zones[x] > 0 {
             self.drop_zones[x] -= 1;
             let drop_spot = self.drop_zones[x];
             self.set(x, drop_spot, self.next_move);
             self.next_move = self.next_move.opponent();
+            self.hash ^= self.zobrist.side_to_move;
             Some(drop_spot)
         } else {
             None
@@ -149,45 +283,24 @@ impl Board {
     }
 
     fn update(&mut self, x: usize, y: usize) {
-        // let pos_sets = vec![(-1, -1), (-1, 0), (-1, 1), (0, 1)]
-        //     .iter()
-        //     .map(|(dx, dy)| {
-        //         (-3..=3)
-        //             .map(|d| ((x as i32) + d * dx, (y as i32) + d * dy))
-        //             .filter(|(px, py)| {
-        //                 *px >= 0
-        //                     && *py >= 0
-        //                     && *px < self.grid.width as i32
-        //                     && *py < self.grid.height as i32
-        //             })
-        //             .map(|(px, py)| (px as usize, py as usize))
-        //             .map(|(px, py)| ((px, py), self.grid.get(px, py)))
-        //             .collect::<Vec<_>>()
-        //     })
-        //     .filter(|poses| poses.len() >= 4);
-        // .collect::<Vec<_>>();
-
-        // let all_poses = pos_sets.iter().flatten().map(|(pos, _)| pos).collect::<HashSet<_>>();
-        // self.print_with_pos_set(&all_poses);
-
-        for poses in vec![(-1, -1), (-1, 0), (-1, 1), (0, 1)]
+        let span = self.win_length - 1;
+        let win_length = self.win_length;
+        let rect = self.grid.rect();
+        let center = Coord::new(x, y);
+        for poses in [(-1, -1), (-1, 0), (-1, 1), (0, 1)]
             .iter()
-            .map(|(dx, dy)| {
-                (-3..=3)
-                    .map(|d| ((x as i32) + d * dx, (y as i32) + d * dy))
-                    .filter(|(px, py)| {
-                        *px >= 0
-                            && *py >= 0
-                            && *px < self.grid.width as i32
-                            && *py < self.grid.height as i32
-                    })
-                    .map(|(px, py)| (px as usize, py as usize))
-                    .map(|(px, py)| ((px, py), self.grid.get(px, py)))
+            .map(|&(dx, dy)| {
+                let mut window: Vec<Coord> = rect.line(center, -dx, -dy).take(span).collect();
+                window.reverse();
+                window.push(center);
+                window.extend(rect.line(center, dx, dy).take(span));
+                window
+                    .into_iter()
+                    .map(|c| ((c.x, c.y), self.grid.get(c.x, c.y).unwrap()))
                     .collect::<Vec<_>>()
             })
-            .filter(|poses| poses.len() >= 4)
+            .filter(|poses| poses.len() >= win_length)
         {
-            // self.print_with_pos_set(&poses.iter().map(|(pos, _)| pos).collect());
             let mut tallies = Tallies {
                 red: Vec::new(),
                 yellow: Vec::new(),
@@ -197,17 +310,15 @@ impl Board {
             for i in 0..poses.len() {
                 let (pos, piece) = poses[i];
                 tallies.get(piece).push(pos);
-                if i >= 4 {
-                    let (early_pos, early_piece) = poses[i - 4];
+                if i >= win_length {
+                    let (early_pos, early_piece) = poses[i - win_length];
                     tallies.get(early_piece).retain(|&pos| pos != early_pos);
                 }
-                if i >= 3 {
-                    if tallies.empty.len() == 1 {
-                        let gap = tallies.empty.first().unwrap().clone();
-                        for piece in vec![Piece::Red, Piece::Yellow] {
-                            if tallies.get(&piece).len() == 3 {
-                                self.threats.push((gap, piece));
-                            }
+                if i >= win_length - 1 && tallies.empty.len() == 1 {
+                    let gap = *tallies.empty.first().unwrap();
+                    for piece in [Piece::Red, Piece::Yellow] {
+                        if tallies.get(&piece).len() == win_length - 1 {
+                            self.threats.push((gap, piece));
                         }
                     }
                 }
@@ -215,33 +326,32 @@ impl Board {
         }
     }
 
-    fn print_with_pos_set(&self, pos_set: &HashSet<&Position>) {
-        for sy in 0..self.grid.height {
-            print!("| ");
-            for sx in 0..self.grid.width {
-                let piece = self.grid.get(sx, sy);
-                let char = if pos_set.contains(&(sx, sy)) {
-                    "*"
-                } else {
-                    match piece {
-                        Piece::Red => "0",
-                        Piece::Yellow => "O",
-                        Piece::Empty => {
-                            let red_threat = self.threats.contains(&((sx, sy), Piece::Red));
-                            let yellow_threat = self.threats.contains(&((sx, sy), Piece::Yellow));
-                            match (red_threat, yellow_threat) {
-                                (true, true) => "B",
-                                (true, false) => "R",
-                                (false, true) => "Y",
-                                (false, false) => " ",
-                            }
-                        }
+    // Scans the board for `win_length` in a row of `self.winner`'s color. Used once
+    // the game has ended to highlight the winning cells in the TUI.
+    pub(crate) fn winning_line(&self) -> Option<Vec<Position>> {
+        let piece = self.winner?;
+        let rect = self.grid.rect();
+        for start in rect.cells() {
+            if *self.grid.get(start.x, start.y).unwrap() != piece {
+                continue;
+            }
+            for &(dx, dy) in &[(1i32, 0i32), (0, 1), (1, 1), (1, -1)] {
+                let mut line = vec![(start.x, start.y)];
+                for c in rect.line(start, dx, dy) {
+                    if *self.grid.get(c.x, c.y).unwrap() != piece {
+                        break;
                     }
-                };
-                print!("{} ", char);
+                    line.push((c.x, c.y));
+                    if line.len() == self.win_length {
+                        break;
+                    }
+                }
+                if line.len() == self.win_length {
+                    return Some(line);
+                }
             }
-            println!("|");
         }
+        None
     }
 }
 
@@ -250,16 +360,15 @@ impl Search for Board {
     type Iter = BoardMoveIterator;
 
     fn score(&self) -> i32 {
-        match self.winner {
-            Some(piece) => {
-                if piece == Piece::Yellow {
-                    return i32::MIN;
-                } else {
-                    return i32::MAX;
-                }
+        if let Some(piece) = self.winner {
+            if piece == Piece::Yellow {
+                return i32::MIN;
+            } else {
+                return i32::MAX;
             }
-            None => (),
         }
+        // `self.threats` already only holds gaps with `win_length - 1` pieces
+        // lined up (see `update`), so the threshold carries over for free.
         self.threats
             .iter()
             .map(|&((_, y), piece)| {
@@ -279,21 +388,26 @@ impl Search for Board {
     fn moves(&self) -> BoardMoveIterator {
         BoardMoveIterator {
             board: self.clone(),
-            move_index: 0,
+            order_index: 0,
         }
     }
 
     fn game_over(&self) -> bool {
         self.winner.is_some() || self.drop_zones.iter().all(|x| *x == 0)
     }
+
+    fn hash_key(&self) -> u64 {
+        self.hash
+    }
 }
 
 impl Display for Board {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.grid.height {
+        let rect = self.grid.rect();
+        for y in 0..rect.height {
             write!(f, "| ")?;
-            for x in 0..self.grid.width {
-                let piece = self.grid.get(x, y);
+            for x in 0..rect.width {
+                let piece = self.grid.get(x, y).copied().unwrap_or(Piece::Empty);
                 let char = match piece {
                     Piece::Red => "0",
                     Piece::Yellow => "O",
@@ -326,6 +440,8 @@ impl PartialEq for Board {
     }
 }
 
+impl Eq for Board {}
+
 impl std::hash::Hash for Board {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.grid.hash(state);
@@ -335,143 +451,567 @@ impl std::hash::Hash for Board {
 
 struct BoardMoveIterator {
     board: Board,
-    move_index: usize,
+    order_index: usize,
 }
 
 impl Iterator for BoardMoveIterator {
     type Item = Board;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.move_index < self.board.drop_zones.len()
-            && self.board.drop_zones[self.move_index] == 0
-        {
-            self.move_index += 1;
-        }
-        if self.move_index < self.board.drop_zones.len() {
-            let mut new_board = self.board.clone();
-            new_board.drop(self.move_index);
-            self.move_index += 1;
-            Some(new_board)
-        } else {
-            None
+        while self.order_index < self.board.move_order.len() {
+            let x = self.board.move_order[self.order_index];
+            self.order_index += 1;
+            if self.board.drop_zones[x] > 0 {
+                let mut new_board = self.board.clone();
+                new_board.drop(x);
+                return Some(new_board);
+            }
         }
+        None
     }
 }
 
+// Root moves paired with the column dropped to reach them, in the board's
+// center-first search order. Used wherever a caller needs to report which
+// column an evaluated child board came from (move ordering, status display).
+fn root_moves(board: &Board) -> Vec<(usize, Board)> {
+    board
+        .move_order
+        .iter()
+        .copied()
+        .filter(|&x| board.drop_zones[x] > 0)
+        .map(|x| {
+            let mut child = board.clone();
+            child.drop(x);
+            (x, child)
+        })
+        .collect()
+}
+
+// Finds the column dropped to get from `before` to `after` by diffing
+// `drop_zones`.
+pub(crate) fn column_played(before: &Board, after: &Board) -> usize {
+    before
+        .drop_zones
+        .iter()
+        .zip(after.drop_zones.iter())
+        .position(|(b, a)| b != a)
+        .expect("after must be reachable from before by exactly one drop")
+}
+
+// Bound flag for a transposition table entry: whether the stored score is
+// exact, or only a lower/upper bound because a cutoff ended the search early.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flag {
+    Exact,
+    LowerBound,
+    UpperBound,
+}
+
+pub(crate) type TranspositionTable<T> = HashMap<u64, (usize, <T as Search>::Score, Flag)>;
+
 fn minimax<T: Search>(
     state: &T,
     depth: usize,
-    alpha: Option<T::Score>,
-    beta: Option<T::Score>,
+    mut alpha: Option<T::Score>,
+    mut beta: Option<T::Score>,
     maximizing: bool,
+    tt: &mut TranspositionTable<T>,
 ) -> T::Score {
     let omax = |a: Option<T::Score>, b: T::Score| a.map_or(Some(b), |v| Some(v.max(b)));
     let omin = |a: Option<T::Score>, b: T::Score| a.map_or(Some(b), |v| Some(v.min(b)));
-    // let mut hasher = DefaultHasher::new();
-    // state.hash(&mut hasher);
-    // let hash = hasher.finish();
-    // println!("Parent {} at depth {} with score {}:", hash, depth, state.score());
-    // println!("{}", state);
-    if depth == 0 || state.game_over() {
+
+    let key = state.hash_key();
+    if let Some(&(stored_depth, stored_score, flag)) = tt.get(&key) {
+        if stored_depth >= depth {
+            match flag {
+                Flag::Exact => return stored_score,
+                Flag::LowerBound => alpha = omax(alpha, stored_score),
+                Flag::UpperBound => beta = omin(beta, stored_score),
+            }
+            if let (Some(a), Some(b)) = (alpha, beta) {
+                if b <= a {
+                    return stored_score;
+                }
+            }
+        }
+    }
+
+    let orig_alpha = alpha;
+    let orig_beta = beta;
+    let result = if depth == 0 || state.game_over() {
         state.score()
     } else if maximizing {
         let mut max_eval: Option<T::Score> = None;
         let mut new_alpha: Option<T::Score> = alpha;
         for child in state.moves() {
-            // println!("Max child of {} with score {}:", hash, child.score());
-            // println!("{}", child);
-            let child_score = minimax(&child, depth - 1, new_alpha, beta, false);
+            let child_score = minimax(&child, depth - 1, new_alpha, beta, false, tt);
             max_eval = omax(max_eval, child_score);
             new_alpha = omax(new_alpha, child_score);
-            // if beta.map_or(true, |b| new_alpha.map_or(true, |a| b <= a)) {
-            //     break;
-            // }
+            if beta.is_some_and(|b| new_alpha.is_some_and(|a| b <= a)) {
+                break;
+            }
         }
         max_eval.unwrap_or_else(|| state.score())
     } else {
         let mut min_eval: Option<T::Score> = None;
         let mut new_beta = beta;
         for child in state.moves() {
-            // println!("Min child of {} with score {}:", hash, child.score());
-            // println!("{}", child);
-            let child_score = minimax(&child, depth - 1, alpha, new_beta, true);
+            let child_score = minimax(&child, depth - 1, alpha, new_beta, true, tt);
             min_eval = omin(min_eval, child_score);
             new_beta = omin(new_beta, child_score);
-            // if new_beta.map_or(true, |b| alpha.map_or(true, |a| b <= a)) {
-            //     break;
-            // }
+            if new_beta.is_some_and(|b| alpha.is_some_and(|a| b <= a)) {
+                break;
+            }
         }
         min_eval.unwrap_or_else(|| state.score())
-    }
-}
+    };
 
-fn main() {
-    let mut board = Board::new();
-    board.show_threats = true;
+    let flag = match (orig_alpha, orig_beta) {
+        (Some(a), _) if result <= a => Flag::UpperBound,
+        (_, Some(b)) if result >= b => Flag::LowerBound,
+        _ => Flag::Exact,
+    };
+    tt.insert(key, (depth, result, flag));
 
-    // let moves = vec![0, 1, 2, 2, 1, 3, 2, 3, 4, 3];
-    // let moves = vec![0, 1, 1, 3, 6, 3, 6, 3];
-    // let moves = vec![3, 3, 3, 6, 5, 5, 2, 6, 2];
-    // for mv in moves {
-    //     board.drop(mv);
-    // }
+    result
+}
 
-    loop {
-        print!("{}", board);
-        if board.next_move == Piece::Yellow {
-            println!("{} move:", board.next_move);
-            let mut ply_move = String::new();
-            stdin().read_line(&mut ply_move).unwrap();
-            match ply_move.trim().parse::<usize>() {
-                Err(_) => {
-                    println!("Type a number");
-                    continue;
+// Young-brothers-wait variant of `minimax`: the first child of every node is
+// searched serially to establish a tight alpha/beta window, then the
+// remaining siblings are fanned out across the rayon thread pool. Shares a
+// transposition table with every other call in the same search via a
+// `Mutex`, locked only for the probe and the final store so the recursive
+// calls themselves still run unsynchronized. Used for the root search and
+// any recursive call beneath it; `minimax` itself stays single-threaded and
+// is kept around as a deterministic fallback.
+fn minimax_parallel<T>(
+    state: &T,
+    depth: usize,
+    mut alpha: Option<T::Score>,
+    mut beta: Option<T::Score>,
+    maximizing: bool,
+    tt: &Mutex<TranspositionTable<T>>,
+) -> T::Score
+where
+    T: Search + Sync,
+    T::Score: Send + Sync,
+    T::Iter: Send,
+{
+    let omax = |a: Option<T::Score>, b: T::Score| a.map_or(Some(b), |v| Some(v.max(b)));
+    let omin = |a: Option<T::Score>, b: T::Score| a.map_or(Some(b), |v| Some(v.min(b)));
+
+    let key = state.hash_key();
+    if let Some(&(stored_depth, stored_score, flag)) = tt.lock().unwrap().get(&key) {
+        if stored_depth >= depth {
+            match flag {
+                Flag::Exact => return stored_score,
+                Flag::LowerBound => alpha = omax(alpha, stored_score),
+                Flag::UpperBound => beta = omin(beta, stored_score),
+            }
+            if let (Some(a), Some(b)) = (alpha, beta) {
+                if b <= a {
+                    return stored_score;
                 }
-                Ok(move_x) => {
-                    if move_x >= board.grid.width {
-                        println!("Type a number from 0 - {}", board.grid.width - 1);
-                        continue;
-                    } else if board.drop_zones[move_x] <= 0 {
-                        println!("Can't move there");
-                        continue;
-                    } else {
-                        board.drop(move_x);
+            }
+        }
+    }
+
+    let orig_alpha = alpha;
+    let orig_beta = beta;
+    let result = if depth == 0 || state.game_over() {
+        state.score()
+    } else {
+        let mut children = state.moves();
+        match children.next() {
+            None => state.score(),
+            Some(first_child) => {
+                let rest: Vec<T> = children.collect();
+                if maximizing {
+                    let mut max_eval =
+                        minimax_parallel(&first_child, depth - 1, alpha, beta, false, tt);
+                    let new_alpha = omax(alpha, max_eval);
+                    for score in rest
+                        .par_iter()
+                        .map(|child| minimax_parallel(child, depth - 1, new_alpha, beta, false, tt))
+                        .collect::<Vec<_>>()
+                    {
+                        max_eval = max_eval.max(score);
                     }
+                    max_eval
+                } else {
+                    let mut min_eval =
+                        minimax_parallel(&first_child, depth - 1, alpha, beta, true, tt);
+                    let new_beta = omin(beta, min_eval);
+                    for score in rest
+                        .par_iter()
+                        .map(|child| minimax_parallel(child, depth - 1, alpha, new_beta, true, tt))
+                        .collect::<Vec<_>>()
+                    {
+                        min_eval = min_eval.min(score);
+                    }
+                    min_eval
                 }
             }
-        } else {
-            let moves_scores = board
-                .moves()
-                .into_iter()
-                .map(|b| (minimax(&b, 5, None, None, false), b))
-                .collect::<Vec<_>>();
-            for (score, _) in moves_scores.iter() {
-                print!("{}, ", score);
-            }
-            println!();
-            if moves_scores.is_empty() {
-                println!("Tie, nobody wins (this should never occur)");
+        }
+    };
+
+    let flag = match (orig_alpha, orig_beta) {
+        (Some(a), _) if result <= a => Flag::UpperBound,
+        (_, Some(b)) if result >= b => Flag::LowerBound,
+        _ => Flag::Exact,
+    };
+    tt.lock().unwrap().insert(key, (depth, result, flag));
+
+    result
+}
+
+const MCTS_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+fn reward_for(winner: Option<Piece>) -> f64 {
+    match winner {
+        Some(Piece::Red) => 1.0,
+        Some(Piece::Yellow) => -1.0,
+        _ => 0.0,
+    }
+}
+
+// Plays uniformly random legal drops to the end of the game and scores the
+// outcome +1/0/-1 from `winner`, same convention as `reward_for`.
+fn simulate(board: &Board) -> f64 {
+    let mut playout = board.clone();
+    let mut rng = rand::thread_rng();
+    while !playout.game_over() {
+        let available: Vec<usize> = (0..playout.drop_zones.len())
+            .filter(|&x| playout.drop_zones[x] > 0)
+            .collect();
+        let column = *available.choose(&mut rng).unwrap();
+        playout.drop(column);
+    }
+    reward_for(playout.winner)
+}
+
+// One node of a UCT tree: the board reached to get here, the moves not yet
+// expanded into children, and the visit count / total reward used by UCB1.
+struct MctsNode {
+    board: Board,
+    visits: u32,
+    reward: f64,
+    children: Vec<MctsNode>,
+    unexpanded: Vec<Board>,
+}
+
+impl MctsNode {
+    fn new(board: Board) -> MctsNode {
+        let unexpanded = board.moves().collect();
+        MctsNode {
+            board,
+            visits: 0,
+            reward: 0.0,
+            children: Vec::new(),
+            unexpanded,
+        }
+    }
+}
+
+// Runs one selection/expansion/simulation/backpropagation pass starting at
+// `node` and returns the reward to fold into the parent's total.
+fn mcts_iterate(node: &mut MctsNode) -> f64 {
+    node.visits += 1;
+    let result = if node.board.game_over() {
+        reward_for(node.board.winner)
+    } else if let Some(child_board) = node.unexpanded.pop() {
+        let mut child = MctsNode::new(child_board);
+        let result = simulate(&child.board);
+        child.visits += 1;
+        child.reward += result;
+        node.children.push(child);
+        result
+    } else {
+        let parent_visits = node.visits;
+        let mover = node.board.next_move;
+        let best = node
+            .children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| {
+                let mean = child.reward / child.visits as f64;
+                let exploitation = if mover == Piece::Yellow { -mean } else { mean };
+                let exploration =
+                    MCTS_EXPLORATION * ((parent_visits as f64).ln() / child.visits as f64).sqrt();
+                (index, exploitation + exploration)
+            })
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(index, _)| index)
+            .unwrap();
+        mcts_iterate(&mut node.children[best])
+    };
+    node.reward += result;
+    result
+}
+
+// Builds a UCT tree rooted at `board`, runs it for `iterations` passes, and
+// returns the root child with the most visits alongside each expanded root
+// child's visit count (for status display).
+pub(crate) fn mcts_search(board: &Board, iterations: usize) -> (Board, Vec<(usize, u32)>) {
+    let mut root = MctsNode::new(board.clone());
+    for _ in 0..iterations {
+        mcts_iterate(&mut root);
+    }
+    let stats = root
+        .children
+        .iter()
+        .map(|child| (column_played(board, &child.board), child.visits))
+        .collect();
+    let best = root
+        .children
+        .into_iter()
+        .max_by_key(|child| child.visits)
+        .map(|child| child.board)
+        .unwrap_or_else(|| board.clone());
+    (best, stats)
+}
+
+pub(crate) enum Agent {
+    Minimax,
+    Mcts,
+}
+
+// Searches depth 1, 2, 3, ... against a shared transposition table, trying
+// the previous depth's best move first. Each root move is searched with an
+// open alpha/beta window, so every column gets its own full score; no bound
+// is carried from one root move to the next. Stops as soon as `time_budget`
+// is exceeded and returns the best move found at the last depth that
+// completed in full.
+pub(crate) fn iterative_deepening(
+    board: &Board,
+    time_budget: Duration,
+    serial: bool,
+    tt: &mut TranspositionTable<Board>,
+) -> (Board, Vec<(usize, i32)>) {
+    let start = Instant::now();
+    let moves = root_moves(board);
+    let mut best = moves.first().expect("no legal moves").1.clone();
+    let mut best_scores: Vec<(usize, i32)> = Vec::new();
+    let mut depth = 1;
+    let mut last_depth_duration: Option<Duration> = None;
+    loop {
+        if start.elapsed() >= time_budget {
+            break;
+        }
+
+        // Unlike the serial path, which can bail between individual root
+        // moves, the non-serial path commits to a whole depth's parallel
+        // batch the moment it's launched. Estimate the next depth's cost
+        // from the last one (scaled by the branching factor) and skip it
+        // up front rather than overshooting the budget once it's underway.
+        if let Some(prev) = last_depth_duration {
+            let remaining = time_budget.saturating_sub(start.elapsed());
+            if prev.saturating_mul(moves.len() as u32) > remaining {
                 break;
             }
-            let max_score = moves_scores.iter().map(|(s, _)| s).max().unwrap();
-            let max_scores = moves_scores
-                .iter()
-                .filter(|(s, _)| s == max_score)
-                .map(|(_, b)| b)
-                .collect::<Vec<_>>();
-            let new_board = *max_scores.choose(&mut rand::thread_rng()).unwrap();
-            board = new_board.clone();
         }
-        match board.winner {
-            Some(winner) => {
-                println!("{} wins!", winner);
-                break;
+
+        let mut ordered = moves.clone();
+        if let Some(pos) = ordered.iter().position(|(_, b)| *b == best) {
+            let preferred = ordered.remove(pos);
+            ordered.insert(0, preferred);
+        }
+
+        let depth_start = Instant::now();
+        let depth_scores: Vec<(usize, i32, Board)> = if serial {
+            let mut scores = Vec::with_capacity(ordered.len());
+            for (column, child) in ordered {
+                if start.elapsed() >= time_budget {
+                    break;
+                }
+                let score = minimax(&child, depth - 1, None, None, false, tt);
+                scores.push((column, score, child));
             }
-            None => (),
+            scores
+        } else {
+            let shared_tt = Mutex::new(std::mem::take(tt));
+            let scores = ordered
+                .into_par_iter()
+                .map(|(column, child)| {
+                    let score = minimax_parallel(&child, depth - 1, None, None, false, &shared_tt);
+                    (column, score, child)
+                })
+                .collect();
+            *tt = shared_tt.into_inner().unwrap();
+            scores
         };
-        if board.drop_zones.iter().all(|x| *x == 0usize) {
-            println!("Tie, nobody wins");
+
+        last_depth_duration = Some(depth_start.elapsed());
+
+        if depth_scores.len() < moves.len() {
             break;
         }
+
+        let max_score = depth_scores.iter().map(|(_, s, _)| *s).max().unwrap();
+        let max_entries: Vec<&(usize, i32, Board)> = depth_scores
+            .iter()
+            .filter(|(_, s, _)| *s == max_score)
+            .collect();
+        best = max_entries
+            .choose(&mut rand::thread_rng())
+            .unwrap()
+            .2
+            .clone();
+        best_scores = depth_scores.iter().map(|(c, s, _)| (*c, *s)).collect();
+        depth += 1;
+    }
+    (best, best_scores)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut threads: Option<usize> = None;
+    let mut serial = false;
+    let mut agent = Agent::Minimax;
+    let mut mcts_iterations: usize = 2000;
+    let mut time_budget = Duration::from_secs_f64(1.0);
+    let mut width: usize = 7;
+    let mut height: usize = 6;
+    let mut win_length: usize = DEFAULT_WIN_LENGTH;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--threads" => {
+                i += 1;
+                threads = args.get(i).and_then(|s| s.parse().ok());
+            }
+            "--serial" => serial = true,
+            "--agent" => {
+                i += 1;
+                agent = match args.get(i).map(String::as_str) {
+                    Some("mcts") => Agent::Mcts,
+                    _ => Agent::Minimax,
+                };
+            }
+            "--mcts-iterations" => {
+                i += 1;
+                if let Some(n) = args.get(i).and_then(|s| s.parse().ok()) {
+                    mcts_iterations = n;
+                }
+            }
+            "--time-budget" => {
+                i += 1;
+                if let Some(secs) = args.get(i).and_then(|s| s.parse::<f64>().ok()) {
+                    time_budget = Duration::from_secs_f64(secs);
+                }
+            }
+            "--width" => {
+                i += 1;
+                if let Some(n) = args.get(i).and_then(|s| s.parse().ok()) {
+                    width = n;
+                }
+            }
+            "--height" => {
+                i += 1;
+                if let Some(n) = args.get(i).and_then(|s| s.parse().ok()) {
+                    height = n;
+                }
+            }
+            "--win-length" => {
+                i += 1;
+                if let Some(n) = args.get(i).and_then(|s| s.parse().ok()) {
+                    win_length = n;
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    if let Some(n) = threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(n)
+            .build_global()
+            .expect("failed to configure rayon thread pool");
+    }
+
+    let board = Board::new_with_options(width, height, win_length);
+
+    if let Err(err) = tui::run(board, agent, serial, mcts_iterations, time_budget) {
+        eprintln!("terminal UI error: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The transposition table keys on `hash_key()`, so two move orders that
+    // reach the same position must hash the same. Clone a shared base board
+    // so both branches draw from the same Zobrist table.
+    #[test]
+    fn hash_matches_across_move_orders() {
+        // Two plies per player, so swapping which pair goes first doesn't
+        // change which color lands in which column (a single swapped ply
+        // would hand the swapped columns to the other player's color).
+        let base = Board::new_with_options(7, 6, 4);
+
+        let mut a = base.clone();
+        a.drop(3); // Yellow
+        a.drop(5); // Red
+        a.drop(2); // Yellow
+        a.drop(6); // Red
+
+        let mut b = base.clone();
+        b.drop(2); // Yellow
+        b.drop(6); // Red
+        b.drop(3); // Yellow
+        b.drop(5); // Red
+
+        assert!(a.grid == b.grid, "boards should match regardless of move order");
+        assert_eq!(a.hash_key(), b.hash_key());
+    }
+
+    // With a connect-3 rule, three in a row should win even though it's one
+    // short of the classic connect-4 threshold.
+    #[test]
+    fn win_length_three_detects_three_in_a_row() {
+        let mut board = Board::new_with_options(7, 6, 3);
+        board.drop(0); // Yellow
+        board.drop(0); // Red
+        board.drop(1); // Yellow
+        board.drop(1); // Red
+        board.drop(2); // Yellow completes three in a row along the bottom row
+
+        assert_eq!(board.winner, Some(Piece::Yellow));
+    }
+
+    // A requested win_length of 0 used to underflow the usize subtraction in
+    // `update` and panic on the very first drop; it should be floored to 1.
+    #[test]
+    fn win_length_zero_does_not_panic() {
+        let mut board = Board::new_with_options(7, 6, 0);
+        board.drop(0);
+    }
+
+    // The non-serial path launches a whole depth's parallel batch at once
+    // and can't bail mid-depth the way the serial path bails between moves,
+    // so iterative_deepening must estimate and skip a depth up front rather
+    // than badly overshoot the time budget once it's running.
+    #[test]
+    fn iterative_deepening_parallel_respects_time_budget() {
+        let board = Board::new_with_options(9, 9, 4);
+        let budget = Duration::from_millis(200);
+        let start = Instant::now();
+        let mut tt = HashMap::new();
+        iterative_deepening(&board, budget, false, &mut tt);
+        assert!(start.elapsed() < budget * 3);
+    }
+
+    #[test]
+    fn grid_get_is_bounds_checked() {
+        let mut grid = Grid::new(3, 2, Piece::Empty);
+
+        assert!(grid.get(2, 1).is_some());
+        assert!(grid.get(3, 0).is_none());
+        assert!(grid.get(0, 2).is_none());
+
+        assert!(grid.get_mut(2, 1).is_some());
+        assert!(grid.get_mut(3, 0).is_none());
     }
 }
@@ -0,0 +1,211 @@
+use std::io::{stdin, stdout, Write};
+use std::thread;
+use std::time::Duration;
+
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::{clear, color, cursor};
+
+use crate::{column_played, iterative_deepening, mcts_search, Agent, Board, Piece, Position};
+use std::collections::HashMap;
+
+const FRAME_DELAY: Duration = Duration::from_millis(60);
+
+// Renders the board in place: colored discs, threat highlighting (reusing
+// `Board`'s own threat tracking), a cursor marker under the human's column,
+// and a status line underneath for engine evaluations or game-over text.
+fn render(
+    out: &mut impl Write,
+    board: &Board,
+    cursor_col: usize,
+    highlight: &[Position],
+    status: &str,
+) -> std::io::Result<()> {
+    write!(out, "{}{}", clear::All, cursor::Goto(1, 1))?;
+    for y in 0..board.grid.height {
+        write!(out, "| ")?;
+        for x in 0..board.grid.width {
+            let piece = board.grid.get(x, y).copied().unwrap_or(Piece::Empty);
+            let highlighted = highlight.contains(&(x, y));
+            let ch = match piece {
+                Piece::Red => "0",
+                Piece::Yellow => "O",
+                Piece::Empty => {
+                    let red_threat = board.threats.contains(&((x, y), Piece::Red));
+                    let yellow_threat = board.threats.contains(&((x, y), Piece::Yellow));
+                    if board.show_threats {
+                        match (red_threat, yellow_threat) {
+                            (true, true) => "B",
+                            (true, false) => "R",
+                            (false, true) => "Y",
+                            (false, false) => " ",
+                        }
+                    } else {
+                        " "
+                    }
+                }
+            };
+            match (piece, highlighted) {
+                (Piece::Red, true) => write!(
+                    out,
+                    "{}{}{} ",
+                    color::Bg(color::White),
+                    color::Fg(color::Red),
+                    ch
+                )?,
+                (Piece::Red, false) => write!(out, "{}{} ", color::Fg(color::Red), ch)?,
+                (Piece::Yellow, true) => write!(
+                    out,
+                    "{}{}{} ",
+                    color::Bg(color::White),
+                    color::Fg(color::Yellow),
+                    ch
+                )?,
+                (Piece::Yellow, false) => write!(out, "{}{} ", color::Fg(color::Yellow), ch)?,
+                _ => write!(out, "{} ", ch)?,
+            }
+            write!(
+                out,
+                "{}{}",
+                color::Fg(color::Reset),
+                color::Bg(color::Reset)
+            )?;
+        }
+        write!(out, "|\r\n")?;
+    }
+    write!(out, "  ")?;
+    for x in 0..board.grid.width {
+        write!(out, "{} ", if x == cursor_col { "^" } else { " " })?;
+    }
+    write!(out, "\r\n{}\r\n", status)?;
+    out.flush()
+}
+
+// Plays the falling-disc animation for a drop into `column`, settling at
+// `dest_row`, by repainting the board with the disc one row lower each frame.
+fn animate_drop(
+    out: &mut impl Write,
+    board: &Board,
+    cursor_col: usize,
+    column: usize,
+    dest_row: usize,
+    piece: Piece,
+) -> std::io::Result<()> {
+    let mut frame = board.clone();
+    for y in 0..=dest_row {
+        if let Some(cell) = frame.grid.get_mut(column, y) {
+            *cell = piece;
+        }
+        render(out, &frame, cursor_col, &[], "")?;
+        if let Some(cell) = frame.grid.get_mut(column, y) {
+            *cell = Piece::Empty;
+        }
+        thread::sleep(FRAME_DELAY);
+    }
+    Ok(())
+}
+
+fn format_scores(scores: &[(usize, i32)]) -> String {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by_key(|(column, _)| *column);
+    sorted
+        .iter()
+        .map(|(column, score)| format!("{}:{}", column, score))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn format_visits(stats: &[(usize, u32)]) -> String {
+    let mut sorted = stats.to_vec();
+    sorted.sort_by_key(|(column, _)| *column);
+    sorted
+        .iter()
+        .map(|(column, visits)| format!("{}:{}", column, visits))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+// Interactive event loop: the human moves a cursor over columns with the
+// arrow keys and drops with Enter, the engine replies with whichever agent
+// was selected on the command line, and both moves are animated falling down
+// `drop_zones`. Replaces the old line-based `stdin`/`println!` loop in main.
+pub(crate) fn run(
+    mut board: Board,
+    agent: Agent,
+    serial: bool,
+    mcts_iterations: usize,
+    time_budget: Duration,
+) -> std::io::Result<()> {
+    board.show_threats = true;
+    let mut out = stdout().into_raw_mode()?;
+    let mut keys = stdin().keys();
+    let mut cursor_col = board.grid.width / 2;
+
+    render(&mut out, &board, cursor_col, &[], "")?;
+
+    'game: loop {
+        if board.next_move == Piece::Yellow {
+            match keys.next() {
+                Some(Ok(Key::Left)) => {
+                    cursor_col = cursor_col.saturating_sub(1);
+                }
+                Some(Ok(Key::Right)) if cursor_col + 1 < board.grid.width => {
+                    cursor_col += 1;
+                }
+                Some(Ok(Key::Char('\n'))) if board.drop_zones[cursor_col] > 0 => {
+                    let dest_row = board.drop_zones[cursor_col] - 1;
+                    animate_drop(
+                        &mut out,
+                        &board,
+                        cursor_col,
+                        cursor_col,
+                        dest_row,
+                        board.next_move,
+                    )?;
+                    board.drop(cursor_col);
+                }
+                Some(Ok(Key::Ctrl('c'))) | Some(Ok(Key::Esc)) | None => break 'game,
+                _ => (),
+            }
+            render(&mut out, &board, cursor_col, &[], "")?;
+        } else {
+            let (next_board, status) = match agent {
+                Agent::Mcts => {
+                    let (result, stats) = mcts_search(&board, mcts_iterations);
+                    (result, format_visits(&stats))
+                }
+                Agent::Minimax => {
+                    let mut tt = HashMap::new();
+                    let (result, scores) =
+                        iterative_deepening(&board, time_budget, serial, &mut tt);
+                    (result, format_scores(&scores))
+                }
+            };
+            let column = column_played(&board, &next_board);
+            let dest_row = board.drop_zones[column] - 1;
+            animate_drop(
+                &mut out,
+                &board,
+                cursor_col,
+                column,
+                dest_row,
+                board.next_move,
+            )?;
+            board = next_board;
+            render(&mut out, &board, cursor_col, &[], &status)?;
+        }
+
+        if board.winner.is_some() || board.drop_zones.iter().all(|&x| x == 0) {
+            let winning_cells = board.winning_line().unwrap_or_default();
+            let status = match board.winner {
+                Some(winner) => format!("{} wins!", winner),
+                None => "Tie, nobody wins".to_string(),
+            };
+            render(&mut out, &board, cursor_col, &winning_cells, &status)?;
+            break 'game;
+        }
+    }
+
+    Ok(())
+}